@@ -110,6 +110,27 @@ impl ColorF {
         ColorU { r: color[0] as u8, g: color[1] as u8, b: color[2] as u8, a: color[3] as u8 }
     }
 
+    /// Applies the sRGB EOTF to each color channel (alpha is untouched), tagging the result as
+    /// linear-light so it can't accidentally be lerped or composited against an sRGB-encoded
+    /// `ColorF` without going through `LinearColorF::to_srgb()` first.
+    #[inline]
+    pub fn to_linear(&self) -> LinearColorF {
+        LinearColorF(ColorF(Self::map_rgb(self.0, srgb_channel_to_linear)))
+    }
+
+    /// Interpolates two sRGB-encoded colors in linear light, which is the physically-correct
+    /// space to blend gradients and translucent fills in, then converts the result back to
+    /// sRGB for display or storage.
+    #[inline]
+    pub fn lerp_linear(&self, other: ColorF, t: f32) -> ColorF {
+        self.to_linear().lerp(other.to_linear(), t).to_srgb()
+    }
+
+    #[inline]
+    fn map_rgb(color: F32x4, f: fn(f32) -> f32) -> F32x4 {
+        F32x4::new(f(color[0]), f(color[1]), f(color[2]), color[3])
+    }
+
     #[inline]
     pub fn lerp(&self, other: ColorF, t: f32) -> ColorF {
         ColorF(self.0 + (other.0 - self.0) * F32x4::splat(t))
@@ -148,3 +169,193 @@ impl Debug for ColorF {
         )
     }
 }
+
+/// A `ColorF` known to hold linear-light (rather than sRGB-encoded) channel values, as
+/// produced by `ColorF::to_linear()`. Keeping this as its own type rather than a runtime flag
+/// on `ColorF` itself means `ColorF`'s layout and public tuple constructor are unchanged, so
+/// every existing `ColorF(channels)` call site in the rest of Pathfinder keeps compiling;
+/// renderer stages that want to assert they're blending linear values can require
+/// `LinearColorF` in their signature instead of a plain `ColorF`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinearColorF(pub ColorF);
+
+impl LinearColorF {
+    /// Applies the inverse sRGB EOTF to each color channel (alpha is untouched), returning the
+    /// sRGB-encoded equivalent of this color.
+    #[inline]
+    pub fn to_srgb(&self) -> ColorF {
+        ColorF(ColorF::map_rgb((self.0).0, linear_channel_to_srgb))
+    }
+
+    #[inline]
+    pub fn lerp(&self, other: LinearColorF, t: f32) -> LinearColorF {
+        LinearColorF(self.0.lerp(other.0, t))
+    }
+}
+
+#[inline]
+fn srgb_channel_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[inline]
+fn linear_channel_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// A separable blend mode, as used by SWF display objects and SVG/CSS groups. "Separable"
+/// means each channel is blended independently of the others, unlike the non-separable modes
+/// (hue, saturation, color, luminosity) which mix channels together.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    HardLight,
+    SoftLight,
+}
+
+impl BlendMode {
+    // `cb` is the backdrop (destination) channel, `cs` is the source channel, both straight
+    // (non-premultiplied) and in `[0.0, 1.0]`. See the W3C Compositing and Blending spec for
+    // the formulas this implements.
+    fn blend_channel(self, cb: f32, cs: f32) -> f32 {
+        match self {
+            BlendMode::Normal => cs,
+            BlendMode::Multiply => cb * cs,
+            BlendMode::Screen => cb + cs - cb * cs,
+            BlendMode::Overlay => BlendMode::HardLight.blend_channel(cs, cb),
+            BlendMode::Darken => cb.min(cs),
+            BlendMode::Lighten => cb.max(cs),
+            BlendMode::HardLight => {
+                if cs <= 0.5 {
+                    BlendMode::Multiply.blend_channel(cb, 2.0 * cs)
+                } else {
+                    BlendMode::Screen.blend_channel(cb, 2.0 * cs - 1.0)
+                }
+            }
+            BlendMode::SoftLight => {
+                if cs <= 0.5 {
+                    cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+                } else {
+                    let d = if cb <= 0.25 {
+                        ((16.0 * cb - 12.0) * cb + 4.0) * cb
+                    } else {
+                        cb.sqrt()
+                    };
+                    cb + (2.0 * cs - 1.0) * (d - cb)
+                }
+            }
+        }
+    }
+}
+
+impl ColorF {
+    /// Blends `self` (the source) over `backdrop` (the destination) using `mode`, then
+    /// composites the result with Porter-Duff source-over alpha compositing. Both colors are
+    /// expected to be premultiplied and in linear light; the result is premultiplied linear
+    /// as well, ready to be composited again or converted back with `LinearColorF::to_srgb()`.
+    pub fn blend(&self, backdrop: ColorF, mode: BlendMode) -> ColorF {
+        let src_a = self.a();
+        let dst_a = backdrop.a();
+
+        let src = self.unpremultiply();
+        let dst = backdrop.unpremultiply();
+        let blended = F32x4::new(
+            mode.blend_channel(dst.r(), src.r()),
+            mode.blend_channel(dst.g(), src.g()),
+            mode.blend_channel(dst.b(), src.b()),
+            0.0,
+        );
+
+        let src_rgb = F32x4::new(self.r(), self.g(), self.b(), 0.0);
+        let dst_rgb = F32x4::new(backdrop.r(), backdrop.g(), backdrop.b(), 0.0);
+        let out_rgb = src_rgb * F32x4::splat(1.0 - dst_a)
+            + dst_rgb * F32x4::splat(1.0 - src_a)
+            + blended * F32x4::splat(src_a * dst_a);
+        let out_a = src_a + dst_a - src_a * dst_a;
+
+        ColorF(F32x4::new(out_rgb[0], out_rgb[1], out_rgb[2], out_a))
+    }
+
+    /// Divides the color channels by alpha, turning a premultiplied color back into a
+    /// straight-alpha one. Returns `self` unchanged if fully transparent, since there's no
+    /// sensible straight-alpha color to recover in that case.
+    #[inline]
+    fn unpremultiply(&self) -> ColorF {
+        let a = self.a();
+        if a == 0.0 {
+            return *self;
+        }
+        ColorF::new(self.r() / a, self.g() / a, self.b() / a, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: ColorF, b: ColorF) {
+        for i in 0..4 {
+            assert!((a.0[i] - b.0[i]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn to_linear_then_to_srgb_round_trips() {
+        let color = ColorF::new(0.8, 0.25, 0.02, 0.5);
+        assert_close(color.to_linear().to_srgb(), color);
+    }
+
+    #[test]
+    fn srgb_and_linear_channel_conversions_round_trip_at_known_values() {
+        for &c in &[0.0, 0.02, 0.04045, 0.25, 0.5, 1.0] {
+            let round_tripped = linear_channel_to_srgb(srgb_channel_to_linear(c));
+            assert!((round_tripped - c).abs() < 1e-5, "{} != {}", round_tripped, c);
+        }
+    }
+
+    #[test]
+    fn multiply_blend_channel_matches_known_values() {
+        assert_eq!(BlendMode::Multiply.blend_channel(1.0, 1.0), 1.0);
+        assert_eq!(BlendMode::Multiply.blend_channel(1.0, 0.0), 0.0);
+        assert_eq!(BlendMode::Multiply.blend_channel(0.5, 0.5), 0.25);
+    }
+
+    #[test]
+    fn screen_blend_channel_matches_known_values() {
+        assert_eq!(BlendMode::Screen.blend_channel(0.0, 0.0), 0.0);
+        assert_eq!(BlendMode::Screen.blend_channel(1.0, 0.0), 1.0);
+        assert_eq!(BlendMode::Screen.blend_channel(0.5, 0.5), 0.75);
+    }
+
+    #[test]
+    fn darken_and_lighten_blend_channel_pick_the_expected_extreme() {
+        assert_eq!(BlendMode::Darken.blend_channel(0.2, 0.8), 0.2);
+        assert_eq!(BlendMode::Lighten.blend_channel(0.2, 0.8), 0.8);
+    }
+
+    #[test]
+    fn blend_over_fully_transparent_backdrop_returns_the_source_unchanged() {
+        let source = ColorU::new(10, 20, 30, 128).to_f32();
+        let backdrop = ColorF::transparent_black();
+        assert_close(source.blend(backdrop, BlendMode::Multiply), source);
+    }
+
+    #[test]
+    fn unpremultiply_divides_straight_through_alpha() {
+        let color = ColorF::new(0.25, 0.5, 0.75, 0.5);
+        assert_close(color.unpremultiply(), ColorF::new(0.5, 1.0, 1.5, 0.5));
+    }
+}
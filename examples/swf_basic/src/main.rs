@@ -25,9 +25,10 @@ use pathfinder_renderer::options::{BuildOptions, RenderTransform};
 use pathfinder_renderer::scene::Scene;
 use pathfinder_resources::embedded::EmbeddedResourceLoader;
 use pathfinder_resources::ResourceLoader;
-use pathfinder_swf::{draw_paths_into_scene, process_swf_tags};
+use pathfinder_swf::{draw_paths_into_scene, Timeline};
 use std::env;
 use std::fs::read;
+use std::time::{Duration, Instant};
 
 fn main() {
     let resource_loader = EmbeddedResourceLoader;
@@ -69,14 +70,15 @@ fn main() {
     let (_, movie): (_, swf_types::Movie) =
         swf_parser::streaming::movie::parse_movie(&swf_bytes[..]).unwrap();
 
-    // process swf scene
-    // TODO(jon): Since swf is a streaming format, this really wants to be a lazy iterator over
-    // swf frames eventually.
-    let (library, stage) = process_swf_tags(&movie);
+    // Drive the movie's display list frame by frame instead of flattening it into a single
+    // static scene, so placed objects, depth ordering, and color transforms animate correctly.
+    let mut timeline = Timeline::new(&movie);
+    timeline.next_frame();
+    let frame_duration = Duration::from_secs_f32(1.0 / timeline.stage().frame_rate());
 
     // Calculate the right logical size of the window.
     let event_loop = EventLoop::new();
-    let window_size = vec2i(stage.width(), stage.height());
+    let window_size = vec2i(timeline.stage().width(), timeline.stage().height());
     let logical_window_size = LogicalSize::new(window_size.x(), window_size.y());
 
     // Open a window.
@@ -101,7 +103,7 @@ fn main() {
     let device = GLDevice::new(GLVersion::GL3, 0);
     let mode = RendererMode::default_for_device(&device);
     let options = RendererOptions {
-        background_color: Some(stage.background_color()),
+        background_color: Some(timeline.stage().background_color()),
         dest: DestFramebuffer::full_window(vec2i(
             physical_size.width as i32,
             physical_size.height as i32,
@@ -110,15 +112,16 @@ fn main() {
     };
     let mut renderer = Renderer::new(device, &EmbeddedResourceLoader, mode, options);
 
-    let device_pixel_ratio = physical_size.width as f32 / stage.width() as f32;
+    let device_pixel_ratio = physical_size.width as f32 / timeline.stage().width() as f32;
 
-    // Clear to swf stage background color.
+    // Clear to swf stage background color and draw the current frame's display list.
     let mut scene = Scene::new();
     scene.set_view_box(RectF::new(
         Vector2F::zero(),
-        vec2f(stage.width() as f32, stage.height() as f32) * device_pixel_ratio,
+        vec2f(timeline.stage().width() as f32, timeline.stage().height() as f32)
+            * device_pixel_ratio,
     ));
-    draw_paths_into_scene(&library, &mut scene);
+    draw_paths_into_scene(timeline.display_list(), timeline.library(), &mut scene);
 
     // Render the canvas to screen.
     let mut scene = SceneProxy::from_scene(scene, renderer.mode().level, RayonExecutor);
@@ -129,8 +132,10 @@ fn main() {
     println!("{:?}", renderer.last_rendering_time());
 
     gl_context.swap_buffers().unwrap();
+
+    let mut next_frame_time = Instant::now() + frame_duration;
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+        *control_flow = ControlFlow::WaitUntil(next_frame_time);
 
         match event {
             Event::WindowEvent {
@@ -155,7 +160,8 @@ fn main() {
                 event: WindowEvent::Resized(physical_size),
                 ..
             } => {
-                let device_pixel_ratio = physical_size.width as f32 / stage.width() as f32;
+                let device_pixel_ratio =
+                    physical_size.width as f32 / timeline.stage().width() as f32;
 
                 gl_context.resize(physical_size);
 
@@ -169,9 +175,26 @@ fn main() {
                 build_options.transform = RenderTransform::Transform2D(scale_transform);
                 scene.set_view_box(RectF::new(
                     Vector2F::zero(),
-                    vec2f(stage.width() as f32, stage.height() as f32) * device_pixel_ratio,
+                    vec2f(timeline.stage().width() as f32, timeline.stage().height() as f32)
+                        * device_pixel_ratio,
                 ));
             }
+            Event::NewEvents(_) if Instant::now() >= next_frame_time => {
+                // Loop back to the first frame once the movie has played through, rather
+                // than freezing on the last one.
+                if !timeline.next_frame() {
+                    timeline = Timeline::new(&movie);
+                    timeline.next_frame();
+                }
+
+                let mut new_scene = Scene::new();
+                new_scene.set_view_box(scene.view_box());
+                draw_paths_into_scene(timeline.display_list(), timeline.library(), &mut new_scene);
+                scene.replace_scene(new_scene);
+                gl_context.window().request_redraw();
+
+                next_frame_time += frame_duration;
+            }
             Event::RedrawRequested(_) => {
                 scene.build_and_render(&mut renderer, build_options.clone());
                 println!("{:?}", renderer.last_rendering_time());
@@ -0,0 +1,171 @@
+// pathfinder/examples/swf_wasm/src/lib.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The `wasm32-unknown-unknown` counterpart to `examples/swf_basic`: same SWF playback loop,
+//! driven over `pathfinder_wgpu` instead of `pathfinder_gl::GLDevice`, so it can run against a
+//! WebGPU (or WebGL2-via-`wgpu`) canvas in the browser instead of a native GL context.
+
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{vec2f, vec2i, Vector2F};
+use pathfinder_renderer::concurrent::rayon::RayonExecutor;
+use pathfinder_renderer::concurrent::scene_proxy::SceneProxy;
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererMode, RendererOptions};
+use pathfinder_renderer::gpu::renderer::Renderer;
+use pathfinder_renderer::options::{BuildOptions, RenderTransform};
+use pathfinder_renderer::scene::Scene;
+use pathfinder_resources::embedded::EmbeddedResourceLoader;
+use pathfinder_resources::ResourceLoader;
+use pathfinder_swf::{draw_paths_into_scene, Timeline};
+use pathfinder_wgpu::WgpuDevice;
+use wasm_bindgen::prelude::*;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::platform::web::WindowExtWebSys;
+use winit::window::WindowBuilder;
+
+const DEFAULT_TIGER_SWF: &[u8] = include_bytes!("../../../resources/swf/tiger.swf");
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    console_error_panic_hook::set_once();
+    wasm_bindgen_futures::spawn_local(run());
+}
+
+async fn run() {
+    let resource_loader = EmbeddedResourceLoader;
+
+    let (_, movie): (_, swf_types::Movie) =
+        swf_parser::streaming::movie::parse_movie(DEFAULT_TIGER_SWF).unwrap();
+
+    let mut timeline = Timeline::new(&movie);
+    timeline.next_frame();
+    let frame_duration_ms = 1000.0 / timeline.stage().frame_rate() as f64;
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_inner_size(winit::dpi::LogicalSize::new(
+            timeline.stage().width(),
+            timeline.stage().height(),
+        ))
+        .build(&event_loop)
+        .unwrap();
+
+    // Hand the winit canvas to the document body; there's no native window to open under wasm.
+    web_sys::window()
+        .and_then(|win| win.document())
+        .and_then(|doc| doc.body())
+        .and_then(|body| body.append_child(&web_sys::Element::from(window.canvas())).ok())
+        .expect("couldn't append canvas to document body");
+
+    let instance = wgpu::Instance::new(wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL);
+    let surface = unsafe { instance.create_surface(&window) }.unwrap();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        })
+        .await
+        .expect("no suitable GPU adapter found");
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .expect("failed to create wgpu device");
+
+    let physical_size = window.inner_size();
+    let surface_format = surface.get_capabilities(&adapter).formats[0];
+    surface.configure(
+        &device,
+        &wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: physical_size.width,
+            height: physical_size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        },
+    );
+
+    let pf_device = WgpuDevice::new(device, queue);
+    let mode = RendererMode::default_for_device(&pf_device);
+    let options = RendererOptions {
+        background_color: Some(timeline.stage().background_color()),
+        dest: DestFramebuffer::full_window(vec2i(
+            physical_size.width as i32,
+            physical_size.height as i32,
+        )),
+        ..RendererOptions::default()
+    };
+    let mut renderer = Renderer::new(pf_device, &EmbeddedResourceLoader, mode, options);
+
+    let device_pixel_ratio = physical_size.width as f32 / timeline.stage().width() as f32;
+
+    let build_scene = |timeline: &Timeline| {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(
+            Vector2F::zero(),
+            vec2f(timeline.stage().width() as f32, timeline.stage().height() as f32)
+                * device_pixel_ratio,
+        ));
+        draw_paths_into_scene(timeline.display_list(), timeline.library(), &mut scene);
+        scene
+    };
+
+    let mut scene = SceneProxy::from_scene(
+        build_scene(&timeline),
+        renderer.mode().level,
+        RayonExecutor,
+    );
+    let mut build_options = BuildOptions::default();
+    build_options.transform = RenderTransform::Transform2D(Transform2F::from_scale(device_pixel_ratio));
+
+    let mut last_frame_time_ms = 0.0;
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            Event::MainEventsCleared => {
+                let now_ms = now_ms();
+                if now_ms - last_frame_time_ms >= frame_duration_ms {
+                    last_frame_time_ms = now_ms;
+                    if !timeline.next_frame() {
+                        timeline = Timeline::new(&movie);
+                        timeline.next_frame();
+                    }
+                    scene.replace_scene(build_scene(&timeline));
+                }
+                window.request_redraw();
+            }
+            Event::RedrawRequested(_) => {
+                let frame = surface
+                    .get_current_texture()
+                    .expect("failed to acquire next swap chain texture");
+                let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                renderer.device().set_default_render_target(view, surface_format);
+                scene.build_and_render(&mut renderer, build_options.clone());
+                frame.present();
+            }
+            _ => {}
+        }
+    });
+}
+
+fn now_ms() -> f64 {
+    web_sys::window()
+        .expect("no global `window` exists")
+        .performance()
+        .expect("performance API unavailable")
+        .now()
+}
@@ -0,0 +1,676 @@
+// pathfinder/swf/src/lib.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for turning a parsed SWF (Adobe Flash) movie into Pathfinder paths.
+//!
+//! SWF is a streaming format: a movie is really a sequence of frames, and the set of shapes
+//! visible on stage (the "display list") changes over time as `PlaceObject`/`RemoveObject`
+//! tags are processed between `ShowFrame` tags. [`Timeline`] models this directly instead of
+//! flattening the whole movie into a single static scene.
+
+use pathfinder_color::ColorU;
+use pathfinder_content::gradient::{Gradient, GradientGeometry};
+use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::{Image, Pattern};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{vec2f, vec2i};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{DrawPath, Scene};
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use swf_types as swf;
+
+/// A resolved SWF fill style, in shape-local space. Unlike the raw `swf_types::FillStyle`, the
+/// gradient square and bitmap matrix have already been folded in; `resolve_paint` still applies
+/// the placed object's transform and color transform on top when turning these into a `Paint`.
+pub enum FillStyle {
+    Solid(ColorU),
+    LinearGradient(GradientFill),
+    RadialGradient(GradientFill),
+    Bitmap { bitmap_id: u16, matrix: Transform2F },
+}
+
+/// A gradient fill's shape-local geometry and raw (not yet color-transformed) stops. The
+/// `Gradient` paint itself is built lazily in `resolve_paint`, once the placed object's own
+/// transform and color transform are both known, since both have to be folded in before the
+/// gradient can be turned into scene-space geometry and final stop colors.
+#[derive(Clone)]
+pub struct GradientFill {
+    is_radial: bool,
+    square_to_shape: Transform2F,
+    stops: Vec<(ColorU, f32)>,
+}
+
+/// A single decoded SWF character. Only shapes are supported today.
+pub struct Character {
+    /// The paths that make up this shape, each with the fill style it should be drawn with.
+    pub paths: Vec<(Outline, FillStyle)>,
+}
+
+/// Every character definition seen so far while walking a movie's tags.
+#[derive(Default)]
+pub struct Library {
+    pub characters: HashMap<u16, Character>,
+    pub bitmaps: HashMap<u16, Arc<Image>>,
+}
+
+/// Movie-wide metadata. Size, frame rate, and frame count come from the SWF header; the
+/// background color is always opaque white, since `SetBackgroundColor` tags are not read.
+pub struct Stage {
+    width: i32,
+    height: i32,
+    background_color: ColorU,
+    frame_rate: f32,
+    frame_count: u16,
+}
+
+impl Stage {
+    #[inline]
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn background_color(&self) -> ColorU {
+        self.background_color
+    }
+
+    #[inline]
+    pub fn frame_rate(&self) -> f32 {
+        self.frame_rate
+    }
+
+    #[inline]
+    pub fn frame_count(&self) -> u16 {
+        self.frame_count
+    }
+}
+
+fn stage_from_header(movie: &swf::Movie) -> Stage {
+    let header = &movie.header;
+    Stage {
+        width: (header.stage.x_max - header.stage.x_min) / 20,
+        height: (header.stage.y_max - header.stage.y_min) / 20,
+        background_color: ColorU::new(255, 255, 255, 255),
+        frame_rate: header.frame_rate,
+        frame_count: header.frame_count,
+    }
+}
+
+/// Multiplicative/additive color transform applied by a `PlaceObject` tag, as described in
+/// the SWF spec. Mirrors the shape of the blend operations in `pathfinder_color`, but this is
+/// deliberately kept simple (no premultiplication) since it's just a linear remap of an
+/// 8-bit color, not a compositing operation.
+#[derive(Clone, Copy)]
+pub struct ColorTransform {
+    pub mult: [f32; 4],
+    pub add: [f32; 4],
+}
+
+impl Default for ColorTransform {
+    #[inline]
+    fn default() -> ColorTransform {
+        ColorTransform {
+            mult: [1.0, 1.0, 1.0, 1.0],
+            add: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl ColorTransform {
+    pub fn apply(&self, color: ColorU) -> ColorU {
+        let channels = [color.r, color.g, color.b, color.a];
+        let mut out = [0u8; 4];
+        for i in 0..4 {
+            let value = channels[i] as f32 * self.mult[i] + self.add[i];
+            out[i] = value.max(0.0).min(255.0) as u8;
+        }
+        ColorU::new(out[0], out[1], out[2], out[3])
+    }
+}
+
+/// A character placed on stage at a particular depth, as tracked by the display list.
+#[derive(Clone)]
+pub struct PlacedObject {
+    pub character_id: u16,
+    pub depth: i16,
+    pub matrix: Transform2F,
+    pub color_transform: ColorTransform,
+}
+
+/// The set of characters currently placed on stage, keyed by depth (SWF's stacking order).
+#[derive(Default)]
+pub struct DisplayList {
+    objects: BTreeMap<i16, PlacedObject>,
+}
+
+impl DisplayList {
+    /// Iterates placed objects back-to-front, i.e. in the order they should be drawn.
+    pub fn iter(&self) -> impl Iterator<Item = &PlacedObject> {
+        self.objects.values()
+    }
+
+    fn place(&mut self, tag: &swf::tags::PlaceObject) {
+        let existing = self.objects.get(&tag.depth);
+        let matrix = tag.matrix.map(matrix_to_transform);
+        let color_transform = tag.color_transform.map(color_transform_from_swf);
+        let placed = Self::merge_placement(tag.depth, tag.character_id, matrix, color_transform, existing);
+        if let Some(placed) = placed {
+            self.objects.insert(tag.depth, placed);
+        }
+    }
+
+    /// Merges a `PlaceObject` tag's (possibly partial) fields with whatever is already placed
+    /// at `depth`, if anything: SWF lets a `PlaceObject` tag move or recolor an existing
+    /// character by depth without repeating every field, so any field the tag leaves unset
+    /// falls back to the existing placement's. Returns `None` if there's neither a new
+    /// character nor an existing one to fall back on.
+    fn merge_placement(
+        depth: i16,
+        character_id: Option<u16>,
+        matrix: Option<Transform2F>,
+        color_transform: Option<ColorTransform>,
+        existing: Option<&PlacedObject>,
+    ) -> Option<PlacedObject> {
+        let character_id = character_id.or_else(|| existing.map(|object| object.character_id))?;
+        Some(PlacedObject {
+            character_id,
+            depth,
+            matrix: matrix
+                .or_else(|| existing.map(|object| object.matrix))
+                .unwrap_or_default(),
+            color_transform: color_transform
+                .or_else(|| existing.map(|object| object.color_transform))
+                .unwrap_or_default(),
+        })
+    }
+
+    fn remove(&mut self, depth: i16) {
+        self.objects.remove(&depth);
+    }
+}
+
+fn matrix_to_transform(matrix: swf::Matrix) -> Transform2F {
+    Transform2F::row_major(
+        matrix.scale_x,
+        matrix.rotate_skew0,
+        matrix.translate_x as f32 / 20.0,
+        matrix.rotate_skew1,
+        matrix.scale_y,
+        matrix.translate_y as f32 / 20.0,
+    )
+}
+
+fn color_transform_from_swf(transform: swf::ColorTransform) -> ColorTransform {
+    ColorTransform {
+        mult: [
+            transform.red_mult,
+            transform.green_mult,
+            transform.blue_mult,
+            transform.alpha_mult,
+        ],
+        add: [
+            transform.red_add as f32,
+            transform.green_add as f32,
+            transform.blue_add as f32,
+            transform.alpha_add as f32,
+        ],
+    }
+}
+
+/// Walks `movie`'s tags one frame at a time, feeding newly-defined characters into a
+/// [`Library`] and newly-placed/removed objects into a [`DisplayList`] as it goes.
+struct FrameCursor<'a> {
+    tags: &'a [swf::Tag],
+    pos: usize,
+}
+
+impl<'a> FrameCursor<'a> {
+    fn new(movie: &'a swf::Movie) -> FrameCursor<'a> {
+        FrameCursor {
+            tags: &movie.tags,
+            pos: 0,
+        }
+    }
+
+    /// Processes tags up to and including the next `ShowFrame`, returning `false` once the
+    /// movie has no more frames to show.
+    fn advance_to_next_frame(&mut self, library: &mut Library, display_list: &mut DisplayList) -> bool {
+        while self.pos < self.tags.len() {
+            let tag = &self.tags[self.pos];
+            self.pos += 1;
+            match tag {
+                swf::Tag::DefineShape(shape) => {
+                    library.characters.insert(shape.id, decode_shape(shape));
+                }
+                swf::Tag::DefineBitsLossless(bitmap) => {
+                    let image = decode_lossless_bitmap(
+                        bitmap.width,
+                        bitmap.height,
+                        &bitmap.format,
+                        &bitmap.data,
+                        false,
+                    );
+                    library.bitmaps.insert(bitmap.id, Arc::new(image));
+                }
+                swf::Tag::DefineBitsLossless2(bitmap) => {
+                    let image = decode_lossless_bitmap(
+                        bitmap.width,
+                        bitmap.height,
+                        &bitmap.format,
+                        &bitmap.data,
+                        true,
+                    );
+                    library.bitmaps.insert(bitmap.id, Arc::new(image));
+                }
+                // `DefineBitsJpeg2`/`DefineBitsJpeg3`/`DefineBitsJpeg4` store JPEG-compressed
+                // pixel data, which would need an actual JPEG decoder to turn into an `Image`;
+                // shapes that reference one of these simply go unfilled for now rather than
+                // failing the whole movie.
+                swf::Tag::PlaceObject(place) => display_list.place(place),
+                swf::Tag::RemoveObject(remove) => display_list.remove(remove.depth),
+                // Action scripting doesn't affect the visual display list we render, but the
+                // tag still has to be consumed so frame counting stays in sync with the movie.
+                swf::Tag::DoAction(_) => {}
+                swf::Tag::ShowFrame => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+}
+
+/// Plays a [`swf::Movie`] back one frame at a time, maintaining the `Library` of character
+/// definitions and the `DisplayList` of what's currently placed on stage. This is the
+/// incremental counterpart to [`process_swf_tags`], which eagerly flattens the whole movie.
+pub struct Timeline<'a> {
+    stage: Stage,
+    library: Library,
+    display_list: DisplayList,
+    cursor: FrameCursor<'a>,
+}
+
+impl<'a> Timeline<'a> {
+    pub fn new(movie: &'a swf::Movie) -> Timeline<'a> {
+        Timeline {
+            stage: stage_from_header(movie),
+            library: Library::default(),
+            display_list: DisplayList::default(),
+            cursor: FrameCursor::new(movie),
+        }
+    }
+
+    #[inline]
+    pub fn stage(&self) -> &Stage {
+        &self.stage
+    }
+
+    #[inline]
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    #[inline]
+    pub fn display_list(&self) -> &DisplayList {
+        &self.display_list
+    }
+
+    /// Advances to the next frame, updating the display list in place. Returns `false` once
+    /// the movie has ended (at which point the display list is left as it was on the final
+    /// frame, so callers can choose to loop back to frame one or stop rendering).
+    pub fn next_frame(&mut self) -> bool {
+        self.cursor
+            .advance_to_next_frame(&mut self.library, &mut self.display_list)
+    }
+}
+
+/// Decodes every character definition in `movie` into a [`Library`] up front, ignoring the
+/// display list entirely, so placement, depth ordering, and per-frame animation are lost.
+/// [`draw_paths_into_scene`] also needs a [`DisplayList`], which only [`Timeline`] builds;
+/// prefer [`Timeline`] for anything that should play back like an actual movie.
+pub fn process_swf_tags(movie: &swf::Movie) -> (Library, Stage) {
+    let mut library = Library::default();
+    for tag in &movie.tags {
+        match tag {
+            swf::Tag::DefineShape(shape) => {
+                library.characters.insert(shape.id, decode_shape(shape));
+            }
+            swf::Tag::DefineBitsLossless(bitmap) => {
+                let image = decode_lossless_bitmap(
+                    bitmap.width,
+                    bitmap.height,
+                    &bitmap.format,
+                    &bitmap.data,
+                    false,
+                );
+                library.bitmaps.insert(bitmap.id, Arc::new(image));
+            }
+            swf::Tag::DefineBitsLossless2(bitmap) => {
+                let image = decode_lossless_bitmap(
+                    bitmap.width,
+                    bitmap.height,
+                    &bitmap.format,
+                    &bitmap.data,
+                    true,
+                );
+                library.bitmaps.insert(bitmap.id, Arc::new(image));
+            }
+            _ => {}
+        }
+    }
+    (library, stage_from_header(movie))
+}
+
+/// Reverses "source-over" premultiplication of one channel: a premultiplied channel `c` is
+/// always `<= a`, so `c * 255 / a` recovers the straight-alpha value without overflow; fully
+/// transparent pixels (`a == 0`) carry no color information at all, so they just decode to 0.
+fn unpremultiply_channel(c: u8, a: u8) -> u8 {
+    if a == 0 {
+        0
+    } else {
+        (c as u32 * 255 / a as u32) as u8
+    }
+}
+
+/// SWF requires every row of a lossless bitmap's pixel data to be padded to a 32-bit boundary,
+/// so rows can't be walked as one flat `chunks_exact` over `bytes_per_pixel` once a row's byte
+/// length isn't itself a multiple of 4: `row_stride` is that padded length, and only the first
+/// `width` pixels of each row are real data, with the rest silently discarded.
+fn row_stride(width: u16, bytes_per_pixel: usize) -> usize {
+    let row_bytes = width as usize * bytes_per_pixel;
+    (row_bytes + 3) & !3
+}
+
+/// Decodes a `DefineBitsLossless`/`DefineBitsLossless2` tag's pixel data into RGBA8, per the
+/// three lossless bitmap formats the SWF spec defines: 8-bit colormapped, 15-bit RGB, and
+/// 24-bit RGB. `DefineBitsLossless2` reuses the same formats but with an alpha channel folded
+/// in (a per-entry alpha byte for the colormap, and 32-bit premultiplied ARGB in place of
+/// 24-bit RGB).
+fn decode_lossless_bitmap(
+    width: u16,
+    height: u16,
+    format: &swf::BitmapFormat,
+    data: &[u8],
+    has_alpha: bool,
+) -> Image {
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    match format {
+        swf::BitmapFormat::ColorMap8(table) => {
+            let entry_size = if has_alpha { 4 } else { 3 };
+            let stride = row_stride(width, 1);
+            for row in data.chunks(stride) {
+                for &index in &row[..width as usize] {
+                    let entry = &table[index as usize * entry_size..][..entry_size];
+                    pixels.push(entry[0]);
+                    pixels.push(entry[1]);
+                    pixels.push(entry[2]);
+                    pixels.push(if has_alpha { entry[3] } else { 255 });
+                }
+            }
+        }
+        swf::BitmapFormat::Rgb15 => {
+            let stride = row_stride(width, 2);
+            for row in data.chunks(stride) {
+                for chunk in row[..width as usize * 2].chunks_exact(2) {
+                    let packed = u16::from_be_bytes([chunk[0], chunk[1]]);
+                    pixels.push((((packed >> 10) & 0x1f) * 255 / 31) as u8);
+                    pixels.push((((packed >> 5) & 0x1f) * 255 / 31) as u8);
+                    pixels.push(((packed & 0x1f) * 255 / 31) as u8);
+                    pixels.push(255);
+                }
+            }
+        }
+        swf::BitmapFormat::Rgb24 => {
+            if has_alpha {
+                // DefineBitsLossless2's 32-bit truecolor format is premultiplied ARGB, not
+                // straight-alpha RGBA, and 4 bytes/pixel is always already row-aligned.
+                for chunk in data.chunks_exact(4) {
+                    let (a, r, g, b) = (chunk[0], chunk[1], chunk[2], chunk[3]);
+                    pixels.push(unpremultiply_channel(r, a));
+                    pixels.push(unpremultiply_channel(g, a));
+                    pixels.push(unpremultiply_channel(b, a));
+                    pixels.push(a);
+                }
+            } else {
+                let stride = row_stride(width, 3);
+                for row in data.chunks(stride) {
+                    for chunk in row[..width as usize * 3].chunks_exact(3) {
+                        pixels.push(chunk[0]);
+                        pixels.push(chunk[1]);
+                        pixels.push(chunk[2]);
+                        pixels.push(255);
+                    }
+                }
+            }
+        }
+    }
+    Image::new(vec2i(width as i32, height as i32), Arc::new(pixels))
+}
+
+fn decode_shape(shape: &swf::tags::DefineShape) -> Character {
+    let paths = shape
+        .shape
+        .records
+        .iter()
+        .filter_map(|record| decode_shape_record(record))
+        .collect();
+    Character { paths }
+}
+
+fn decode_shape_record(record: &swf::ShapeRecord) -> Option<(Outline, FillStyle)> {
+    let outline = Outline::from_swf_edges(&record.edges);
+    let fill = decode_fill_style(record.fill_style.as_ref()?);
+    Some((outline, fill))
+}
+
+fn decode_fill_style(fill: &swf::FillStyle) -> FillStyle {
+    match fill {
+        swf::FillStyle::Solid(color) => FillStyle::Solid(ColorU::new(color.r, color.g, color.b, color.a)),
+        swf::FillStyle::LinearGradient(gradient) => {
+            FillStyle::LinearGradient(decode_gradient(gradient, false))
+        }
+        swf::FillStyle::RadialGradient(gradient) => {
+            FillStyle::RadialGradient(decode_gradient(gradient, true))
+        }
+        swf::FillStyle::Bitmap(bitmap) => FillStyle::Bitmap {
+            bitmap_id: bitmap.bitmap_id,
+            matrix: matrix_to_transform(bitmap.matrix),
+        },
+    }
+}
+
+fn decode_gradient(gradient: &swf::Gradient, is_radial: bool) -> GradientFill {
+    // SWF defines gradients over a fixed square from -16384 to 16384 twips (32768 units per
+    // side, regardless of the shape they're painted on); folding that scale into the
+    // gradient's own matrix lets it be sampled directly in shape-local space once the shape's
+    // placement transform is applied on top in `resolve_paint`.
+    let square_to_shape = matrix_to_transform(gradient.matrix)
+        * Transform2F::from_scale(vec2f(16384.0 / 20.0, 16384.0 / 20.0));
+    let stops = gradient
+        .stops
+        .iter()
+        .map(|stop| {
+            let color = ColorU::new(stop.color.r, stop.color.g, stop.color.b, stop.color.a);
+            (color, stop.ratio as f32 / 255.0)
+        })
+        .collect();
+    GradientFill { is_radial, square_to_shape, stops }
+}
+
+/// Draws every object on `display_list` into `scene`, resolving each one's character from
+/// `library`, applying its placement matrix, and folding its color transform into the fill
+/// color (for solid and gradient fills) before it becomes a paint.
+pub fn draw_paths_into_scene(display_list: &DisplayList, library: &Library, scene: &mut Scene) {
+    for placed in display_list.iter() {
+        let character = match library.characters.get(&placed.character_id) {
+            Some(character) => character,
+            None => continue,
+        };
+        for (outline, fill) in &character.paths {
+            let outline = outline.clone().transformed(&placed.matrix);
+            let paint = match resolve_paint(fill, &placed.color_transform, library, &placed.matrix) {
+                Some(paint) => paint,
+                None => continue,
+            };
+            let paint_id = scene.push_paint(&paint);
+            scene.push_draw_path(DrawPath::new(outline, paint_id));
+        }
+    }
+}
+
+fn resolve_paint(
+    fill: &FillStyle,
+    color_transform: &ColorTransform,
+    library: &Library,
+    placement: &Transform2F,
+) -> Option<Paint> {
+    match fill {
+        FillStyle::Solid(color) => Some(Paint::from_color(color_transform.apply(*color))),
+        FillStyle::LinearGradient(fill) | FillStyle::RadialGradient(fill) => {
+            // The gradient square's own matrix only maps it into shape-local space; it still
+            // needs the placed object's transform folded in to land in scene space, same as
+            // the outline it's painted on.
+            let square_to_scene = *placement * fill.square_to_shape;
+            let geometry = if fill.is_radial {
+                // SWF radial gradients are always concentric circles growing from the center
+                // of the gradient square, so the focal line has zero length; deriving the
+                // radius from the transformed edge point (rather than a raw matrix element)
+                // keeps it correct under rotation, skew, and non-uniform scale.
+                let center = square_to_scene.transform_point(vec2f(0.0, 0.0));
+                let edge = square_to_scene.transform_point(vec2f(1.0, 0.0));
+                GradientGeometry::Radial { line: center..center, radii: 0.0..(edge - center).length() }
+            } else {
+                GradientGeometry::Linear(
+                    square_to_scene.transform_point(vec2f(-1.0, 0.0))
+                        ..square_to_scene.transform_point(vec2f(1.0, 0.0)),
+                )
+            };
+            let mut result = Gradient::new(geometry);
+            for &(color, stop_ratio) in &fill.stops {
+                result.add_color_stop(color_transform.apply(color), stop_ratio);
+            }
+            Some(Paint::from_gradient(result))
+        }
+        // Color transforms aren't applied to bitmap fills: they tint a sampled texture rather
+        // than a single resolved color, which the `Pattern` paint doesn't support folding in.
+        FillStyle::Bitmap { bitmap_id, matrix } => {
+            let image = library.bitmaps.get(bitmap_id)?.clone();
+            let mut pattern = Pattern::from_image(image);
+            pattern.set_transform(*placement * *matrix);
+            Some(Paint::from_pattern(pattern))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_transform_default_is_identity() {
+        let color = ColorU::new(12, 34, 56, 78);
+        assert_eq!(ColorTransform::default().apply(color), color);
+    }
+
+    #[test]
+    fn color_transform_apply_saturates_at_bounds() {
+        let transform = ColorTransform {
+            mult: [2.0, 2.0, 2.0, 1.0],
+            add: [50.0, -50.0, 0.0, 0.0],
+        };
+        // Channel 0 would overflow 255, channel 1 would go negative; both must clamp rather
+        // than wrap.
+        let result = transform.apply(ColorU::new(200, 10, 0, 255));
+        assert_eq!(result, ColorU::new(255, 0, 0, 255));
+    }
+
+    fn placed(character_id: u16, matrix: Transform2F) -> PlacedObject {
+        PlacedObject {
+            character_id,
+            depth: 1,
+            matrix,
+            color_transform: ColorTransform::default(),
+        }
+    }
+
+    #[test]
+    fn merge_placement_with_no_existing_object_needs_a_character_id() {
+        assert!(DisplayList::merge_placement(1, None, None, None, None).is_none());
+
+        let placed = DisplayList::merge_placement(1, Some(7), None, None, None).unwrap();
+        assert_eq!(placed.character_id, 7);
+        assert_eq!(placed.depth, 1);
+    }
+
+    #[test]
+    fn merge_placement_reuses_existing_fields_left_unset_by_the_tag() {
+        let original_matrix = Transform2F::from_translation(vec2f(10.0, 0.0));
+        let existing = placed(7, original_matrix);
+
+        // A `PlaceObject` that only moves the object (no character id, no color transform)
+        // should keep the existing character and color transform, but adopt the new matrix.
+        let moved_matrix = Transform2F::from_translation(vec2f(20.0, 0.0));
+        let moved = DisplayList::merge_placement(1, None, Some(moved_matrix), None, Some(&existing)).unwrap();
+        assert_eq!(moved.character_id, 7);
+        assert_eq!(moved.matrix, moved_matrix);
+    }
+
+    #[test]
+    fn unpremultiply_channel_recovers_straight_alpha() {
+        // Fully opaque: premultiplication is a no-op, so unpremultiplying is too.
+        assert_eq!(unpremultiply_channel(128, 255), 128);
+        // Half alpha: a premultiplied channel of 128 came from a straight value near 255.
+        assert_eq!(unpremultiply_channel(128, 128), 255);
+        // Fully transparent: no color information survives premultiplication, so this must not
+        // divide by zero.
+        assert_eq!(unpremultiply_channel(0, 0), 0);
+    }
+
+    #[test]
+    fn row_stride_pads_to_a_32_bit_boundary() {
+        // 3 one-byte pixels is 3 bytes, padded up to 4.
+        assert_eq!(row_stride(3, 1), 4);
+        // 4 one-byte pixels is already a multiple of 4.
+        assert_eq!(row_stride(4, 1), 4);
+        // 3 two-byte pixels is 6 bytes, padded up to 8.
+        assert_eq!(row_stride(3, 2), 8);
+    }
+
+    #[test]
+    fn decode_lossless_bitmap_respects_row_padding_for_color_map8() {
+        // A 3-pixel-wide, 2-row ColorMap8 bitmap: each row is 3 index bytes padded to 4, so the
+        // real rows are `[idx, idx, idx, pad]` rather than a flat, unpadded byte stream.
+        let table = vec![10, 20, 30, 40, 50, 60];
+        let format = swf::BitmapFormat::ColorMap8(table);
+        let data = vec![0, 1, 0, 0xff, 1, 0, 1, 0xff];
+        let image = decode_lossless_bitmap(3, 2, &format, &data, false);
+        let pixels = image.pixels();
+        // Second row's first pixel is palette entry 1 (40, 50, 60), not garbage shifted in from
+        // the first row's padding byte.
+        assert_eq!(&pixels[3 * 4..3 * 4 + 3], &[40, 50, 60]);
+    }
+
+    #[test]
+    fn decode_lossless_bitmap_unpremultiplies_argb32() {
+        // Premultiplied ARGB: alpha 128, straight red ~255 stored premultiplied as ~128.
+        let format = swf::BitmapFormat::Rgb24;
+        let data = vec![128, 128, 0, 0];
+        let image = decode_lossless_bitmap(1, 1, &format, &data, true);
+        let pixels = image.pixels();
+        assert_eq!(pixels[0], 255);
+        assert_eq!(pixels[3], 128);
+    }
+}
@@ -0,0 +1,1010 @@
+// pathfinder/wgpu/src/lib.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A `wgpu`-backed implementation of `pathfinder_gpu::Device`.
+//!
+//! This lets `Renderer` run over Vulkan, Metal, DX12, or WebGPU/WebGL2 (via `wgpu`'s own
+//! backend selection) instead of being hardwired to desktop OpenGL through `pathfinder_gl`,
+//! which in turn is what makes it possible to target `wasm32-unknown-unknown` in the browser.
+//! The mapping mirrors `pathfinder_gl::GLDevice` method-for-method; see that crate for the
+//! reference implementation this one is kept in sync with.
+//!
+//! `wgpu` has no notion of a GL-style bound buffer/texture that later calls implicitly read,
+//! so the handles below (`WgpuBuffer`, `WgpuVertexArray`, ...) use interior mutability to
+//! record that state themselves, and every `Device` method here takes `&self` to match.
+
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::{
+    BufferData, BufferTarget, BufferUploadMode, ClearOps, Device, FeatureLevel, RenderState,
+    RenderTarget, ShaderKind, TextureDataRef, TextureFormat, TextureSamplingFlags, UniformData,
+    VertexAttrDescriptor,
+};
+use pathfinder_resources::ResourceLoader;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A GPU buffer together with the capacity it was last allocated with. `wgpu::Buffer`s can't
+/// be resized in place, so `allocate_buffer` replaces `raw` with a freshly-sized buffer
+/// whenever the caller asks for more room than it currently has, mirroring what
+/// `glBufferData` does to a GL buffer name under the hood.
+pub struct WgpuBuffer {
+    raw: RefCell<wgpu::Buffer>,
+    capacity: Cell<u64>,
+    usage: Cell<wgpu::BufferUsages>,
+}
+
+impl WgpuBuffer {
+    fn new(device: &wgpu::Device, usage: wgpu::BufferUsages) -> WgpuBuffer {
+        WgpuBuffer {
+            raw: RefCell::new(Self::alloc(device, usage, 0)),
+            capacity: Cell::new(0),
+            usage: Cell::new(usage),
+        }
+    }
+
+    fn alloc(device: &wgpu::Device, usage: wgpu::BufferUsages, size: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Widens this buffer's usage flags to also cover `usage`, recreating the backing
+    /// `wgpu::Buffer` if the combined flags actually changed. A `wgpu::Buffer`'s usage is
+    /// fixed at creation time, but a `BufferTarget` (and so the usage a caller needs) isn't
+    /// known until `allocate_buffer` is first called, and a buffer can legitimately be
+    /// rebound to a different target later, so this only ever grows the usage set.
+    fn ensure_usage(&self, device: &wgpu::Device, usage: wgpu::BufferUsages) {
+        let combined = self.usage.get() | usage;
+        if combined != self.usage.get() {
+            let buffer = Self::alloc(device, combined, self.capacity.get());
+            self.usage.set(combined);
+            *self.raw.borrow_mut() = buffer;
+        }
+    }
+
+    fn ensure_capacity(&self, device: &wgpu::Device, needed: u64) {
+        if needed > self.capacity.get() {
+            *self.raw.borrow_mut() = Self::alloc(device, self.usage.get(), needed);
+            self.capacity.set(needed);
+        }
+    }
+}
+
+/// A texture backed by a `wgpu::Texture` plus the view the renderer needs to bind or attach
+/// it. Kept as a single handle (rather than separate ones) since they're always created and
+/// destroyed together; the sampler used to read it is tracked separately in
+/// `WgpuDevice::sampler_cache` because its filtering mode can change after creation via
+/// `set_texture_sampling_mode`.
+pub struct WgpuTexture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    size: Vector2I,
+    format: TextureFormat,
+    sampling_flags: Cell<TextureSamplingFlags>,
+}
+
+/// A framebuffer: either the window's own swap chain view, for the final `DestFramebuffer`, or
+/// an offscreen render target backed by a `WgpuTexture`. The swap chain variant carries its
+/// format alongside the view since, unlike an offscreen `WgpuTexture`, it has no `TextureFormat`
+/// of its own to report but a render pipeline targeting it still has to be built against one.
+pub enum WgpuFramebuffer {
+    Texture(WgpuTexture),
+    SwapChain(wgpu::TextureView, wgpu::TextureFormat),
+}
+
+/// A compiled shader module plus its stage; pipelines built from it always use the `main`
+/// entry point.
+pub struct WgpuShader {
+    module: wgpu::ShaderModule,
+    kind: ShaderKind,
+}
+
+/// A vertex attribute slot: just the shader location it's bound to. Kept as its own type
+/// (rather than a bare `u32`) so a future revision can attach the attribute's name for
+/// diagnostics without changing every call site.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WgpuVertexAttr(u32);
+
+/// A uniform slot. `wgpu` has no equivalent of GL's `glUniform*`, which writes a value directly
+/// into a bound program, so the handle itself carries no binding information; the actual bind
+/// group index a uniform lands at is decided positionally, from its place in the `uniforms`
+/// slice a draw call's `RenderState` supplies (see `WgpuDevice::bind_group_for`). The slot
+/// number here exists only to make `get_uniform` return distinct, stable values per name.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WgpuUniform(u32);
+
+/// A linked "program": a vertex/fragment shader pair, plus the name → slot maps that let
+/// `get_vertex_attr`/`get_uniform` hand back stable handles without shader reflection. The
+/// bind group layout a program's draws need depends on how many uniforms/textures a given
+/// `RenderState` actually supplies, which isn't known until draw time, so it's built lazily
+/// and cached by `WgpuDevice::layouts_for` rather than stored here.
+pub struct WgpuProgram {
+    vertex: WgpuShader,
+    fragment: WgpuShader,
+    vertex_attrs: RefCell<HashMap<String, WgpuVertexAttr>>,
+    uniforms: RefCell<HashMap<String, WgpuUniform>>,
+}
+
+struct VertexAttrBinding {
+    buffer: Arc<WgpuBuffer>,
+    descriptor: VertexAttrDescriptor,
+}
+
+/// The set of vertex/index buffer bindings configured for a draw call, equivalent to a GL VAO.
+#[derive(Default)]
+pub struct WgpuVertexArray {
+    attrs: RefCell<HashMap<u32, VertexAttrBinding>>,
+    index_buffer: RefCell<Option<Arc<WgpuBuffer>>>,
+    bound_vertex_buffer: RefCell<Option<Arc<WgpuBuffer>>>,
+}
+
+/// A GPU timer query. `wgpu` reports timings via a `QuerySet` plus a resolve/readback buffer;
+/// both are created together since one is useless without the other.
+pub struct WgpuTimerQuery {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    result: Cell<Option<Duration>>,
+}
+
+/// A pending texture readback. The actual bytes are only available once `recv_texture_data`
+/// maps and waits on `buffer`.
+pub struct WgpuTextureDataReceiver {
+    buffer: wgpu::Buffer,
+    size: Vector2I,
+    bytes_per_row: u32,
+}
+
+/// The `pathfinder_gpu::Device` implementation itself. Holds the `wgpu::Device`/`Queue` pair
+/// the caller set up (over a window surface on desktop, or a canvas surface under wasm), the
+/// in-flight command encoder `Device` methods record into, the pipeline/layout/sampler caches,
+/// and the view that stands in for GL's implicit default framebuffer.
+pub struct WgpuDevice {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    encoder: RefCell<Option<wgpu::CommandEncoder>>,
+    pipeline_cache: RefCell<HashMap<u64, Arc<wgpu::RenderPipeline>>>,
+    /// Bind group layout + pipeline layout pairs, keyed on the program and the uniform/texture
+    /// counts a draw against it actually used. A program's WGSL doesn't change shape between
+    /// draws, so in practice this holds exactly one entry per program, but the count is part
+    /// of the key rather than assumed so a mismatched call fails loudly instead of silently
+    /// reusing the wrong layout.
+    layout_cache:
+        RefCell<HashMap<(u64, usize, usize), (Arc<wgpu::BindGroupLayout>, Arc<wgpu::PipelineLayout>)>>,
+    sampler_cache: RefCell<HashMap<TextureSamplingFlags, Arc<wgpu::Sampler>>>,
+    /// The view and format to render into when a draw call targets `RenderTarget::Default`,
+    /// i.e. the window. The caller must set this once per frame via `set_default_render_target`
+    /// before issuing any draws, since `wgpu` (unlike GL) has no implicit "framebuffer 0".
+    default_target: RefCell<Option<(wgpu::TextureView, wgpu::TextureFormat)>>,
+}
+
+impl WgpuDevice {
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue) -> WgpuDevice {
+        WgpuDevice {
+            device,
+            queue,
+            encoder: RefCell::new(None),
+            pipeline_cache: RefCell::new(HashMap::new()),
+            layout_cache: RefCell::new(HashMap::new()),
+            sampler_cache: RefCell::new(HashMap::new()),
+            default_target: RefCell::new(None),
+        }
+    }
+
+    /// Supplies the view and format to clear/draw into for this frame's `RenderTarget::Default`
+    /// draws. Must be called once per frame (with the swap chain's current view and the
+    /// surface's configured format) before rendering.
+    pub fn set_default_render_target(&self, view: wgpu::TextureView, format: wgpu::TextureFormat) {
+        *self.default_target.borrow_mut() = Some((view, format));
+    }
+
+    fn with_encoder<R>(&self, f: impl FnOnce(&mut wgpu::CommandEncoder) -> R) -> R {
+        let mut encoder_slot = self.encoder.borrow_mut();
+        let encoder = encoder_slot.get_or_insert_with(|| {
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None })
+        });
+        f(encoder)
+    }
+
+    /// Runs `f` with the view to render/clear/read into for `target`, borrowing
+    /// `self.default_target` only for the duration of the call when `target` is `Default`.
+    fn with_target_view<R>(
+        &self,
+        target: &RenderTarget<Self>,
+        f: impl FnOnce(&wgpu::TextureView) -> R,
+    ) -> R {
+        match target {
+            RenderTarget::Default => {
+                let default_target = self.default_target.borrow();
+                let (view, _) = default_target
+                    .as_ref()
+                    .expect("set_default_render_target() must be called before drawing to it");
+                f(view)
+            }
+            RenderTarget::Other(framebuffer) => match framebuffer {
+                WgpuFramebuffer::Texture(texture) => f(&texture.view),
+                WgpuFramebuffer::SwapChain(view, _) => f(view),
+            },
+        }
+    }
+
+    /// The `wgpu::TextureFormat` a render pipeline targeting `target` must be built against.
+    fn target_format(&self, target: &RenderTarget<Self>) -> wgpu::TextureFormat {
+        match target {
+            RenderTarget::Default => {
+                let default_target = self.default_target.borrow();
+                let (_, format) = default_target
+                    .as_ref()
+                    .expect("set_default_render_target() must be called before drawing to it");
+                *format
+            }
+            RenderTarget::Other(WgpuFramebuffer::Texture(texture)) => {
+                Self::wgpu_texture_format(texture.format)
+            }
+            RenderTarget::Other(WgpuFramebuffer::SwapChain(_, format)) => *format,
+        }
+    }
+
+    fn sampler_for(&self, flags: TextureSamplingFlags) -> Arc<wgpu::Sampler> {
+        if let Some(sampler) = self.sampler_cache.borrow().get(&flags) {
+            return sampler.clone();
+        }
+        let filter = if flags.contains(TextureSamplingFlags::NEAREST_FILTER) {
+            wgpu::FilterMode::Nearest
+        } else {
+            wgpu::FilterMode::Linear
+        };
+        let wrap = if flags.contains(TextureSamplingFlags::REPEAT) {
+            wgpu::AddressMode::Repeat
+        } else {
+            wgpu::AddressMode::ClampToEdge
+        };
+        let sampler = Arc::new(self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wrap,
+            address_mode_v: wrap,
+            address_mode_w: wrap,
+            mag_filter: filter,
+            min_filter: filter,
+            ..wgpu::SamplerDescriptor::default()
+        }));
+        self.sampler_cache.borrow_mut().insert(flags, sampler.clone());
+        sampler
+    }
+
+    fn pipeline_for(
+        &self,
+        program: &WgpuProgram,
+        vertex_array: &WgpuVertexArray,
+        render_state: &RenderState<Self>,
+        target_format: wgpu::TextureFormat,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> Arc<wgpu::RenderPipeline> {
+        // Keyed on the identity of the program/vertex-array pair, the target's format, whether
+        // blending is on (the only part of `render_state` that `Self::blend_state` reads), and
+        // the uniform/texture counts that pick the pipeline layout. Pipelines are built with
+        // no depth/stencil state, so none is part of the key.
+        let key = Self::pipeline_key(program, vertex_array, render_state, target_format);
+        if let Some(pipeline) = self.pipeline_cache.borrow().get(&key) {
+            return pipeline.clone();
+        }
+
+        let attrs = vertex_array.attrs.borrow();
+        let mut locations: Vec<_> = attrs.keys().copied().collect();
+        locations.sort_unstable();
+        let wgpu_attrs: Vec<wgpu::VertexAttribute> = locations
+            .iter()
+            .map(|location| {
+                let binding = &attrs[location];
+                wgpu::VertexAttribute {
+                    format: Self::vertex_format(&binding.descriptor),
+                    offset: binding.descriptor.offset as u64,
+                    shader_location: *location,
+                }
+            })
+            .collect();
+        let stride = attrs
+            .values()
+            .map(|binding| binding.descriptor.stride as u64)
+            .max()
+            .unwrap_or(0);
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu_attrs,
+        };
+
+        let pipeline = self
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &program.vertex.module,
+                    entry_point: "main",
+                    buffers: &[vertex_buffer_layout],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &program.fragment.module,
+                    entry_point: "main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: target_format,
+                        blend: Self::blend_state(render_state),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+        let pipeline = Arc::new(pipeline);
+        self.pipeline_cache.borrow_mut().insert(key, pipeline.clone());
+        pipeline
+    }
+
+    fn pipeline_key(
+        program: &WgpuProgram,
+        vertex_array: &WgpuVertexArray,
+        render_state: &RenderState<Self>,
+        target_format: wgpu::TextureFormat,
+    ) -> u64 {
+        let program_ptr = program as *const WgpuProgram as u64;
+        let vertex_array_ptr = vertex_array as *const WgpuVertexArray as u64;
+        let blend_bits = render_state.blend.is_some() as u64;
+        let uniforms_bits = render_state.uniforms.len() as u64;
+        let textures_bits = render_state.textures.len() as u64;
+        program_ptr
+            ^ vertex_array_ptr.rotate_left(1)
+            ^ (blend_bits << 2)
+            ^ (target_format as u64).rotate_left(3)
+            ^ uniforms_bits.rotate_left(5)
+            ^ textures_bits.rotate_left(7)
+    }
+
+    fn vertex_format(descriptor: &VertexAttrDescriptor) -> wgpu::VertexFormat {
+        match (descriptor.size, descriptor.class.is_float()) {
+            (1, true) => wgpu::VertexFormat::Float32,
+            (2, true) => wgpu::VertexFormat::Float32x2,
+            (3, true) => wgpu::VertexFormat::Float32x3,
+            (4, true) => wgpu::VertexFormat::Float32x4,
+            (1, false) => wgpu::VertexFormat::Sint32,
+            (2, false) => wgpu::VertexFormat::Sint32x2,
+            (3, false) => wgpu::VertexFormat::Sint32x3,
+            _ => wgpu::VertexFormat::Sint32x4,
+        }
+    }
+
+    fn blend_state(render_state: &RenderState<Self>) -> Option<wgpu::BlendState> {
+        render_state.blend.as_ref().map(|_| wgpu::BlendState::ALPHA_BLENDING)
+    }
+
+    fn wgpu_texture_format(format: TextureFormat) -> wgpu::TextureFormat {
+        match format {
+            TextureFormat::R8 => wgpu::TextureFormat::R8Unorm,
+            TextureFormat::R16F => wgpu::TextureFormat::R16Float,
+            TextureFormat::RGBA8 => wgpu::TextureFormat::Rgba8Unorm,
+            TextureFormat::RGBA16F => wgpu::TextureFormat::Rgba16Float,
+            TextureFormat::RGBA32F => wgpu::TextureFormat::Rgba32Float,
+        }
+    }
+
+    fn buffer_usage(target: BufferTarget) -> wgpu::BufferUsages {
+        let base = wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        base | match target {
+            BufferTarget::Vertex => wgpu::BufferUsages::VERTEX,
+            BufferTarget::Index => wgpu::BufferUsages::INDEX,
+            BufferTarget::Uniform => wgpu::BufferUsages::UNIFORM,
+            BufferTarget::Storage => wgpu::BufferUsages::STORAGE,
+        }
+    }
+
+    /// Builds (or returns the cached) bind group layout + pipeline layout for a draw that
+    /// supplies `num_uniforms` uniforms and `num_textures` textures: one uniform-buffer binding
+    /// per uniform, followed by a texture-view binding and a sampler binding per texture.
+    /// Binding indices are assigned positionally in that order, matching `bind_group_for`.
+    fn layouts_for(
+        &self,
+        program: &WgpuProgram,
+        num_uniforms: usize,
+        num_textures: usize,
+    ) -> (Arc<wgpu::BindGroupLayout>, Arc<wgpu::PipelineLayout>) {
+        let key = (program as *const WgpuProgram as u64, num_uniforms, num_textures);
+        if let Some(layouts) = self.layout_cache.borrow().get(&key) {
+            return layouts.clone();
+        }
+
+        let mut entries = Vec::with_capacity(num_uniforms + num_textures * 2);
+        for i in 0..num_uniforms {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: i as u32,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+        }
+        for j in 0..num_textures {
+            let base = (num_uniforms + j * 2) as u32;
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: base,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: base + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+
+        let bind_group_layout =
+            Arc::new(self.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &entries,
+            }));
+        let pipeline_layout =
+            Arc::new(self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            }));
+        let layouts = (bind_group_layout, pipeline_layout);
+        self.layout_cache.borrow_mut().insert(key, layouts.clone());
+        layouts
+    }
+
+    /// Packs a `UniformData` value into the little-endian bytes a `wgpu` uniform buffer expects.
+    fn uniform_bytes(data: &UniformData) -> Vec<u8> {
+        match *data {
+            UniformData::Float(value) => value.to_le_bytes().to_vec(),
+            UniformData::Int(value) => value.to_le_bytes().to_vec(),
+            UniformData::TextureUnit(unit) => unit.to_le_bytes().to_vec(),
+            UniformData::IVec3(value) => value.iter().flat_map(|c| c.to_le_bytes()).collect(),
+            UniformData::Vec3(value) => value.iter().flat_map(|c| c.to_le_bytes()).collect(),
+            UniformData::Vec2(value) => {
+                (0..2).flat_map(|i| value[i].to_le_bytes()).collect()
+            }
+            UniformData::Vec4(value) | UniformData::Mat2(value) => {
+                (0..4).flat_map(|i| value[i].to_le_bytes()).collect()
+            }
+            UniformData::Mat4(rows) => rows
+                .iter()
+                .flat_map(|row| (0..4).flat_map(|i| row[i].to_le_bytes()))
+                .collect(),
+        }
+    }
+
+    /// Creates a small uniform buffer holding `bytes` and uploads it immediately; `wgpu` has no
+    /// direct equivalent of `glUniform*`, so every non-texture uniform a draw uses gets its own
+    /// tiny buffer recreated fresh for that draw rather than a single persistent binding.
+    fn uniform_buffer(&self, bytes: &[u8]) -> wgpu::Buffer {
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: bytes.len() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.queue.write_buffer(&buffer, 0, bytes);
+        buffer
+    }
+
+    /// Builds the bind group for one draw from `render_state.uniforms`/`.textures`, in the same
+    /// binding order `layouts_for` laid the group out in.
+    fn bind_group_for(
+        &self,
+        layout: &wgpu::BindGroupLayout,
+        render_state: &RenderState<Self>,
+    ) -> wgpu::BindGroup {
+        let uniform_buffers: Vec<wgpu::Buffer> = render_state
+            .uniforms
+            .iter()
+            .map(|(_, data)| self.uniform_buffer(&Self::uniform_bytes(data)))
+            .collect();
+        let samplers: Vec<Arc<wgpu::Sampler>> = render_state
+            .textures
+            .iter()
+            .map(|(_, texture)| self.sampler_for(texture.sampling_flags.get()))
+            .collect();
+
+        let mut entries = Vec::with_capacity(uniform_buffers.len() + samplers.len() * 2);
+        for (i, buffer) in uniform_buffers.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        let num_uniforms = uniform_buffers.len();
+        for (j, (_, texture)) in render_state.textures.iter().enumerate() {
+            let base = (num_uniforms + j * 2) as u32;
+            entries.push(wgpu::BindGroupEntry {
+                binding: base,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: base + 1,
+                resource: wgpu::BindingResource::Sampler(&samplers[j]),
+            });
+        }
+
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &entries,
+        })
+    }
+
+    fn record_draw(&self, render_state: &RenderState<Self>, draw: impl FnOnce(&mut wgpu::RenderPass)) {
+        let target_format = self.target_format(&render_state.target);
+        let (bind_group_layout, pipeline_layout) = self.layouts_for(
+            render_state.program,
+            render_state.uniforms.len(),
+            render_state.textures.len(),
+        );
+        let pipeline = self.pipeline_for(
+            render_state.program,
+            render_state.vertex_array,
+            render_state,
+            target_format,
+            &pipeline_layout,
+        );
+        let bind_group = self.bind_group_for(&bind_group_layout, render_state);
+        self.with_target_view(&render_state.target, |view| {
+            self.with_encoder(|encoder| {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                if let Some(buffer) = render_state.vertex_array.bound_vertex_buffer.borrow().as_ref() {
+                    pass.set_vertex_buffer(0, buffer.raw.borrow().slice(..));
+                }
+                draw(&mut pass);
+            });
+        });
+    }
+}
+
+impl Device for WgpuDevice {
+    type Buffer = Arc<WgpuBuffer>;
+    type Framebuffer = WgpuFramebuffer;
+    type Program = WgpuProgram;
+    type Shader = WgpuShader;
+    type Texture = WgpuTexture;
+    type TextureDataReceiver = WgpuTextureDataReceiver;
+    type TimerQuery = WgpuTimerQuery;
+    type Uniform = WgpuUniform;
+    type VertexArray = WgpuVertexArray;
+    type VertexAttr = WgpuVertexAttr;
+
+    fn feature_level(&self) -> FeatureLevel {
+        // `FeatureLevel::D3D11` gates `Renderer` onto the compute/storage-buffer tiling path,
+        // which this backend doesn't implement: `create_shader` only builds vertex/fragment
+        // stages and there's no compute dispatch here. Report `D3D10` so `Renderer` sticks to
+        // the raster path this `Device` actually drives.
+        FeatureLevel::D3D10
+    }
+
+    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> WgpuTexture {
+        let wgpu_format = Self::wgpu_texture_format(format);
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: size.x() as u32,
+                height: size.y() as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        WgpuTexture {
+            texture,
+            view,
+            size,
+            format,
+            sampling_flags: Cell::new(TextureSamplingFlags::empty()),
+        }
+    }
+
+    fn create_texture_from_data(
+        &self,
+        format: TextureFormat,
+        size: Vector2I,
+        data: TextureDataRef,
+    ) -> WgpuTexture {
+        let texture = self.create_texture(format, size);
+        self.upload_to_texture(&texture, RectI::new(Vector2I::zero(), size), data);
+        texture
+    }
+
+    fn texture_size(&self, texture: &WgpuTexture) -> Vector2I {
+        texture.size
+    }
+
+    fn texture_format(&self, texture: &WgpuTexture) -> TextureFormat {
+        texture.format
+    }
+
+    fn set_texture_sampling_mode(&self, texture: &WgpuTexture, flags: TextureSamplingFlags) {
+        texture.sampling_flags.set(flags);
+    }
+
+    fn upload_to_texture(&self, texture: &WgpuTexture, rect: RectI, data: TextureDataRef) {
+        let bytes_per_pixel = texture.format.bytes_per_pixel();
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: rect.origin_x() as u32,
+                    y: rect.origin_y() as u32,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            data.as_bytes(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some((rect.width() as u32) * bytes_per_pixel as u32),
+                rows_per_image: Some(rect.height() as u32),
+            },
+            wgpu::Extent3d {
+                width: rect.width() as u32,
+                height: rect.height() as u32,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn create_shader(
+        &self,
+        resources: &dyn ResourceLoader,
+        name: &str,
+        kind: ShaderKind,
+    ) -> WgpuShader {
+        // `wgpu` consumes WGSL (or SPIR-V); the GLSL sources `resources` serves are compiled
+        // to WGSL ahead of time by the build script, mirroring `pathfinder_gl`'s own use of
+        // `resources` to load `.{vs,fs}.glsl` under a different extension.
+        let extension = match kind {
+            ShaderKind::Vertex => "vert.wgsl",
+            ShaderKind::Fragment => "frag.wgsl",
+        };
+        let source = resources
+            .slurp(&format!("shaders/wgpu/{}.{}", name, extension))
+            .unwrap();
+        let module = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(String::from_utf8(source).unwrap().into()),
+        });
+        WgpuShader { module, kind }
+    }
+
+    fn create_program_from_shaders(
+        &self,
+        _resources: &dyn ResourceLoader,
+        _name: &str,
+        vertex_shader: WgpuShader,
+        fragment_shader: WgpuShader,
+    ) -> WgpuProgram {
+        // The bind group/pipeline layout a program needs depends on how many uniforms and
+        // textures a draw against it supplies, which isn't known yet here; `record_draw`
+        // builds (and `layouts_for` caches) those lazily once a `RenderState` is in hand.
+        WgpuProgram {
+            vertex: vertex_shader,
+            fragment: fragment_shader,
+            vertex_attrs: RefCell::new(HashMap::new()),
+            uniforms: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn get_vertex_attr(&self, program: &WgpuProgram, name: &str) -> Option<WgpuVertexAttr> {
+        let mut attrs = program.vertex_attrs.borrow_mut();
+        let next_location = attrs.len() as u32;
+        Some(*attrs
+            .entry(name.to_owned())
+            .or_insert(WgpuVertexAttr(next_location)))
+    }
+
+    fn get_uniform(&self, program: &WgpuProgram, name: &str) -> WgpuUniform {
+        let mut uniforms = program.uniforms.borrow_mut();
+        let next_binding = uniforms.len() as u32;
+        *uniforms
+            .entry(name.to_owned())
+            .or_insert(WgpuUniform(next_binding))
+    }
+
+    fn create_vertex_array(&self) -> WgpuVertexArray {
+        WgpuVertexArray::default()
+    }
+
+    fn bind_buffer(&self, vertex_array: &WgpuVertexArray, buffer: &Arc<WgpuBuffer>, target: BufferTarget) {
+        match target {
+            BufferTarget::Index => {
+                *vertex_array.index_buffer.borrow_mut() = Some(buffer.clone());
+            }
+            BufferTarget::Vertex => {
+                *vertex_array.bound_vertex_buffer.borrow_mut() = Some(buffer.clone());
+            }
+            BufferTarget::Uniform | BufferTarget::Storage => {
+                // Uniform/storage buffers are bound per-draw via `RenderState.uniforms`
+                // rather than on the vertex array, so there's nothing to record here.
+            }
+        }
+    }
+
+    fn configure_vertex_attr(
+        &self,
+        vertex_array: &WgpuVertexArray,
+        attr: &WgpuVertexAttr,
+        descriptor: &VertexAttrDescriptor,
+    ) {
+        let buffer = vertex_array
+            .bound_vertex_buffer
+            .borrow()
+            .clone()
+            .expect("bind_buffer(.., BufferTarget::Vertex) must precede configure_vertex_attr");
+        vertex_array.attrs.borrow_mut().insert(
+            attr.0,
+            VertexAttrBinding {
+                buffer,
+                descriptor: descriptor.clone(),
+            },
+        );
+    }
+
+    fn create_buffer(&self, _mode: BufferUploadMode) -> Arc<WgpuBuffer> {
+        // `allocate_buffer` widens the usage flags to match whatever `BufferTarget` it's
+        // actually bound to the first time it's called; start with just enough to create and
+        // fill an empty buffer.
+        Arc::new(WgpuBuffer::new(
+            &self.device,
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+        ))
+    }
+
+    fn allocate_buffer<T>(&self, buffer: &Arc<WgpuBuffer>, data: BufferData<T>, target: BufferTarget) {
+        buffer.ensure_usage(&self.device, Self::buffer_usage(target));
+        match data {
+            BufferData::Uninitialized(len) => {
+                let needed = (len * std::mem::size_of::<T>()) as u64;
+                buffer.ensure_capacity(&self.device, needed);
+            }
+            BufferData::Memory(slice) => {
+                let bytes: &[u8] = unsafe {
+                    std::slice::from_raw_parts(
+                        slice.as_ptr() as *const u8,
+                        slice.len() * std::mem::size_of::<T>(),
+                    )
+                };
+                buffer.ensure_capacity(&self.device, bytes.len() as u64);
+                self.queue.write_buffer(&buffer.raw.borrow(), 0, bytes);
+            }
+        }
+    }
+
+    fn create_framebuffer(&self, texture: WgpuTexture) -> WgpuFramebuffer {
+        WgpuFramebuffer::Texture(texture)
+    }
+
+    fn framebuffer_texture<'f>(&self, framebuffer: &'f WgpuFramebuffer) -> &'f WgpuTexture {
+        match framebuffer {
+            WgpuFramebuffer::Texture(texture) => texture,
+            WgpuFramebuffer::SwapChain(..) => {
+                panic!("the swap chain framebuffer has no backing texture to hand out")
+            }
+        }
+    }
+
+    fn destroy_framebuffer(&self, framebuffer: WgpuFramebuffer) -> WgpuTexture {
+        match framebuffer {
+            WgpuFramebuffer::Texture(texture) => texture,
+            WgpuFramebuffer::SwapChain(..) => {
+                panic!("the swap chain framebuffer isn't backed by an owned texture")
+            }
+        }
+    }
+
+    fn bind_texture(&self, texture: &WgpuTexture, _unit: u32) {
+        // Binding in `wgpu` means building a bind group, which needs the whole set of
+        // textures/buffers a draw uses at once. That's assembled from `RenderState` when the
+        // draw itself is recorded; this just makes sure a sampler matching the texture's
+        // current filtering mode exists and is cached ahead of time.
+        self.sampler_for(texture.sampling_flags.get());
+    }
+
+    fn read_pixels(&self, target: &RenderTarget<Self>, viewport: RectI) -> WgpuTextureDataReceiver {
+        let bytes_per_row = viewport.width() as u32 * 4;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (bytes_per_row * viewport.height() as u32) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        // Copying pixels out of a `wgpu::TextureView` requires the underlying `Texture`,
+        // which is only available for offscreen framebuffers here; `RenderTarget::Default`
+        // only exposes the swap chain's view, which can't be read back this way.
+        if let RenderTarget::Other(WgpuFramebuffer::Texture(texture)) = target {
+            self.with_encoder(|encoder| {
+                encoder.copy_texture_to_buffer(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d {
+                            x: viewport.origin_x() as u32,
+                            y: viewport.origin_y() as u32,
+                            z: 0,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::ImageCopyBuffer {
+                        buffer: &buffer,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: Some(bytes_per_row),
+                            rows_per_image: Some(viewport.height() as u32),
+                        },
+                    },
+                    wgpu::Extent3d {
+                        width: viewport.width() as u32,
+                        height: viewport.height() as u32,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            });
+        }
+        WgpuTextureDataReceiver {
+            buffer,
+            size: viewport.size(),
+            bytes_per_row,
+        }
+    }
+
+    fn try_recv_texture_data(&self, receiver: &WgpuTextureDataReceiver) -> Option<Vec<u8>> {
+        Some(self.recv_texture_data(receiver))
+    }
+
+    fn recv_texture_data(&self, receiver: &WgpuTextureDataReceiver) -> Vec<u8> {
+        let slice = receiver.buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("failed to map readback buffer");
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        let data = slice.get_mapped_range();
+        let bytes = data.to_vec();
+        drop(data);
+        receiver.buffer.unmap();
+        debug_assert_eq!(
+            bytes.len() as u32,
+            receiver.bytes_per_row * receiver.size.y() as u32
+        );
+        bytes
+    }
+
+    fn create_timer_query(&self) -> WgpuTimerQuery {
+        let query_set = self.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: None,
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        WgpuTimerQuery {
+            query_set,
+            resolve_buffer,
+            result: Cell::new(None),
+        }
+    }
+
+    fn begin_timer_query(&self, query: &WgpuTimerQuery) {
+        self.with_encoder(|encoder| encoder.write_timestamp(&query.query_set, 0));
+    }
+
+    fn end_timer_query(&self, query: &WgpuTimerQuery) {
+        self.with_encoder(|encoder| encoder.write_timestamp(&query.query_set, 1));
+    }
+
+    fn try_recv_timer_query(&self, query: &WgpuTimerQuery) -> Option<Duration> {
+        query.result.get()
+    }
+
+    fn recv_timer_query(&self, query: &WgpuTimerQuery) -> Duration {
+        self.device.poll(wgpu::Maintain::Wait);
+        query.result.get().unwrap_or_default()
+    }
+
+    fn draw_arrays(&self, index_count: u32, render_state: &RenderState<Self>) {
+        self.record_draw(render_state, |pass| {
+            pass.draw(0..index_count, 0..1);
+        });
+    }
+
+    fn draw_elements(&self, index_count: u32, render_state: &RenderState<Self>) {
+        let index_buffer = render_state
+            .vertex_array
+            .index_buffer
+            .borrow()
+            .clone()
+            .expect("draw_elements() requires an index buffer bound via bind_buffer");
+        self.record_draw(render_state, |pass| {
+            pass.set_index_buffer(index_buffer.raw.borrow().slice(..), wgpu::IndexFormat::Uint32);
+            pass.draw_indexed(0..index_count, 0, 0..1);
+        });
+    }
+
+    fn clear(&self, target: &RenderTarget<Self>, ops: &ClearOps) {
+        let load = match ops.color {
+            Some(color) => wgpu::LoadOp::Clear(wgpu::Color {
+                r: color.r() as f64,
+                g: color.g() as f64,
+                b: color.b() as f64,
+                a: color.a() as f64,
+            }),
+            None => wgpu::LoadOp::Load,
+        };
+        self.with_target_view(target, |view| {
+            self.with_encoder(|encoder| {
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("pathfinder clear"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations { load, store: true },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+            });
+        });
+    }
+
+    fn begin_commands(&self) {
+        self.with_encoder(|_| {});
+    }
+
+    fn end_commands(&self) {
+        if let Some(encoder) = self.encoder.borrow_mut().take() {
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+    }
+}